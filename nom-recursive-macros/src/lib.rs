@@ -0,0 +1,454 @@
+//! Procedural macro backing `#[recursive_parser]` / `#[recursive_parser(grow)]` for the
+//! `nom-recursive` crate. See that crate's documentation for usage.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, FnArg, GenericArgument, Ident, ItemFn, Pat,
+    PathArguments, ReturnType, Type,
+};
+
+#[proc_macro_attribute]
+pub fn recursive_parser(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mode = match parse_mode(attr) {
+        Ok(mode) => mode,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let item_fn = parse_macro_input!(item as ItemFn);
+    match expand(mode, item_fn) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Which recursion strategy the expanded prologue should use.
+enum Mode {
+    /// Abort the re-entrant branch as soon as the same rule is re-entered at the same
+    /// position (the original behavior: stops infinite recursion, but still needs a
+    /// terminating alternative to make progress).
+    FailFast,
+    /// Run Warth's seed-growing algorithm so genuine left recursion parses.
+    Grow,
+}
+
+fn parse_mode(attr: TokenStream) -> syn::Result<Mode> {
+    if attr.is_empty() {
+        return Ok(Mode::FailFast);
+    }
+    let ident: Ident = syn::parse(attr)?;
+    if ident == "grow" {
+        Ok(Mode::Grow)
+    } else {
+        Err(syn::Error::new(
+            ident.span(),
+            "expected `grow` or no argument to `#[recursive_parser]`",
+        ))
+    }
+}
+
+/// The parts of a `#[recursive_parser]`-annotated function signature the expansion needs.
+struct Signature {
+    input_ident: Ident,
+    output_ty: Type,
+}
+
+fn extract_signature(sig: &syn::Signature) -> syn::Result<Signature> {
+    let arg = sig.inputs.first().ok_or_else(|| {
+        syn::Error::new(
+            sig.span(),
+            "#[recursive_parser] requires a single input argument",
+        )
+    })?;
+    let input_ident = match arg {
+        FnArg::Typed(pat_ty) => match &*pat_ty.pat {
+            Pat::Ident(pat_ident) => pat_ident.ident.clone(),
+            _ => {
+                return Err(syn::Error::new(
+                    pat_ty.span(),
+                    "#[recursive_parser] requires a simple identifier as its input parameter",
+                ))
+            }
+        },
+        FnArg::Receiver(_) => {
+            return Err(syn::Error::new(
+                arg.span(),
+                "#[recursive_parser] cannot be used on methods",
+            ))
+        }
+    };
+
+    let output_ty = match &sig.output {
+        ReturnType::Type(_, ty) => extract_ires_output(ty)?,
+        ReturnType::Default => {
+            return Err(syn::Error::new(
+                sig.span(),
+                "#[recursive_parser] requires an `IResult<_, _>` return type",
+            ))
+        }
+    };
+
+    Ok(Signature {
+        input_ident,
+        output_ty,
+    })
+}
+
+/// Pulls the `Output` type out of a `nom::IResult<Input, Output>` (or `IResult<Input,
+/// Output, Error>`) return type, so the expansion can annotate the memoization /
+/// seed-growing caches, which are generic over it.
+fn extract_ires_output(ty: &Type) -> syn::Result<Type> {
+    let path = match ty {
+        Type::Path(type_path) => &type_path.path,
+        _ => return Err(syn::Error::new(ty.span(), "expected `IResult<_, _>`")),
+    };
+    let last = path
+        .segments
+        .last()
+        .ok_or_else(|| syn::Error::new(ty.span(), "expected `IResult<_, _>`"))?;
+    if last.ident != "IResult" {
+        return Err(syn::Error::new(
+            ty.span(),
+            "#[recursive_parser] requires an `IResult<_, _>` return type",
+        ));
+    }
+    let args = match &last.arguments {
+        PathArguments::AngleBracketed(args) => &args.args,
+        _ => return Err(syn::Error::new(ty.span(), "expected `IResult<_, _>`")),
+    };
+    match args.iter().nth(1) {
+        Some(GenericArgument::Type(output)) => Ok(output.clone()),
+        _ => Err(syn::Error::new(ty.span(), "expected `IResult<_, Output>`")),
+    }
+}
+
+/// Wraps `call` so it runs under [`nom_recursive::maybe_grow_stack`], spilling onto a
+/// fresh heap segment if the native stack is running low. Decided here, at
+/// nom_recursive_macros' own build time via its `spill-stack` feature (kept in sync with
+/// nom-recursive's own `spill-stack` feature through Cargo feature forwarding), for the
+/// same reason `memoize` is gated this way rather than with a literal `#[cfg]` emitted
+/// into the caller's expanded code.
+fn wrap_stack_spill(call: TokenStream2) -> TokenStream2 {
+    if cfg!(feature = "spill-stack") {
+        quote! { ::nom_recursive::maybe_grow_stack(|| #call) }
+    } else {
+        call
+    }
+}
+
+/// Generates the depth-guard prologue shared by both modes: bumps the recursion-depth
+/// counter on entry, failing fast with `ErrorKind::TooLarge` if the configured
+/// `set_recursion_limit` would be exceeded, and holds the guard for the rest of the
+/// function body so it decrements again on every return path (including early `?`/early
+/// `return`s), via `Drop`.
+fn recursion_guard(input_ident: &Ident) -> TokenStream2 {
+    quote! {
+        let __recursion_guard = match ::nom_recursive::enter_recursion() {
+            Ok(__guard) => __guard,
+            Err(_) => {
+                return Err(::nom::Err::Failure(::nom::error::Error::new(
+                    #input_ident,
+                    ::nom::error::ErrorKind::TooLarge,
+                )));
+            }
+        };
+    }
+}
+
+fn expand(mode: Mode, item_fn: ItemFn) -> syn::Result<TokenStream2> {
+    let signature = extract_signature(&item_fn.sig)?;
+    match mode {
+        Mode::FailFast => expand_fail_fast(item_fn, signature),
+        Mode::Grow => expand_grow(item_fn, signature),
+    }
+}
+
+/// Generates the shared prologue bits (inner fn, rule index lookup) common to both modes.
+struct Prologue {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    sig: syn::Signature,
+    inner_fn: TokenStream2,
+    inner_name: Ident,
+    fn_name_str: String,
+}
+
+fn build_prologue(item_fn: ItemFn) -> Prologue {
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item_fn;
+    let fn_name_str = sig.ident.to_string();
+    let inner_name = Ident::new(&format!("__{}_recursive_body", sig.ident), sig.ident.span());
+    let inputs = &sig.inputs;
+    let output = &sig.output;
+    let generics = &sig.generics;
+    let inner_fn = quote! {
+        fn #inner_name #generics (#inputs) #output #block
+    };
+    Prologue {
+        attrs,
+        vis,
+        sig,
+        inner_fn,
+        inner_name,
+        fn_name_str,
+    }
+}
+
+fn expand_fail_fast(item_fn: ItemFn, signature: Signature) -> syn::Result<TokenStream2> {
+    let Signature {
+        input_ident,
+        output_ty,
+    } = signature;
+    let Prologue {
+        attrs,
+        vis,
+        sig,
+        inner_fn,
+        inner_name,
+        fn_name_str,
+    } = build_prologue(item_fn);
+
+    // Whether to emit the packrat cache check/store is decided here, at
+    // nom_recursive_macros' own build time via its `memoize` feature (kept in sync with
+    // nom-recursive's own `memoize` feature through Cargo feature forwarding) -- NOT by
+    // emitting a `#[cfg(feature = "memoize")]` into the caller's crate, which would
+    // incorrectly check the caller's own Cargo features instead of this library's.
+    let memo_lookup = if cfg!(feature = "memoize") {
+        quote! {
+            let __memo_offset = ::nom_recursive::recursive_offset(&#input_ident);
+            if let Some((__consumed, __value)) = ::nom_recursive::MEMO_STORAGE.with(|storage| {
+                storage.borrow().get::<#output_ty>(__recursive_index, __memo_offset)
+            }) {
+                return Ok((::nom::Slice::slice(&#input_ident, __consumed..), __value));
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+    let memo_store = if cfg!(feature = "memoize") {
+        quote! {
+            let __memo_consumed = ::nom_recursive::recursive_offset(&__rest) - __memo_offset;
+            ::nom_recursive::MEMO_STORAGE.with(|storage| {
+                storage.borrow_mut().insert(
+                    __recursive_index,
+                    __memo_offset,
+                    (__memo_consumed, __value.clone()),
+                )
+            });
+        }
+    } else {
+        TokenStream2::new()
+    };
+
+    let recursion_guard = recursion_guard(&input_ident);
+    let inner_call = wrap_stack_spill(quote! { #inner_name(__flagged_input) });
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            #inner_fn
+
+            #recursion_guard
+
+            let __recursive_index = ::nom_recursive::RECURSIVE_STORAGE
+                .with(|storage| storage.borrow_mut().get(#fn_name_str));
+
+            #memo_lookup
+
+            let __entry_offset = ::nom_recursive::recursive_offset(&#input_ident);
+            let __recursive_info =
+                ::nom_recursive::HasRecursiveInfo::get_recursive_info(&#input_ident);
+            if __recursive_info.check_flag(__recursive_index, __entry_offset) {
+                let mut __trace_info = __recursive_info.clone();
+                __trace_info.push_active(__recursive_index);
+                ::nom_recursive::record_recursion_cycle(
+                    ::nom_recursive::HasRecursiveInfo::recursion_trace(&__trace_info),
+                );
+                return Err(::nom::Err::Error(::nom::error::Error::new(
+                    #input_ident,
+                    ::nom::error::ErrorKind::Fail,
+                )));
+            }
+
+            let mut __flagged_info = __recursive_info;
+            __flagged_info.set_flag(__recursive_index, __entry_offset);
+            __flagged_info.push_active(__recursive_index);
+            let __flagged_input = ::nom_recursive::HasRecursiveInfo::set_recursive_info(
+                #input_ident.clone(),
+                __flagged_info,
+            );
+
+            #inner_call.map(|(__rest, __value)| {
+                let mut __rest_info = ::nom_recursive::HasRecursiveInfo::get_recursive_info(&__rest);
+                __rest_info.clear_flag(__recursive_index);
+                __rest_info.pop_active();
+                let __rest =
+                    ::nom_recursive::HasRecursiveInfo::set_recursive_info(__rest, __rest_info);
+                #memo_store
+                (__rest, __value)
+            })
+        }
+    })
+}
+
+fn expand_grow(item_fn: ItemFn, signature: Signature) -> syn::Result<TokenStream2> {
+    let Signature {
+        input_ident,
+        output_ty,
+    } = signature;
+    let Prologue {
+        attrs,
+        vis,
+        sig,
+        inner_fn,
+        inner_name,
+        fn_name_str,
+    } = build_prologue(item_fn);
+
+    // Same build-time feature gating as `expand_fail_fast`'s memo_lookup/memo_store --
+    // see the comment there. Here the cache is keyed on the *final* grown result for
+    // `(rule, start_offset)`, since re-running the whole seed-growing loop for a rule
+    // that's already fully grown at this position would otherwise happen on every call
+    // (e.g. from backtracking alternatives that retry the same left-recursive rule).
+    let memo_lookup = if cfg!(feature = "memoize") {
+        quote! {
+            if let Some((__consumed, __value)) = ::nom_recursive::MEMO_STORAGE.with(|storage| {
+                storage.borrow().get::<#output_ty>(__recursive_index, __start_offset)
+            }) {
+                return Ok((::nom::Slice::slice(&#input_ident, __consumed..), __value));
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+    let memo_store = if cfg!(feature = "memoize") {
+        quote! {
+            if let Some((__consumed, __value)) = __best.clone() {
+                ::nom_recursive::MEMO_STORAGE.with(|storage| {
+                    storage.borrow_mut().insert(__recursive_index, __start_offset, (__consumed, __value));
+                });
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+
+    let recursion_guard = recursion_guard(&input_ident);
+    let inner_call = wrap_stack_spill(quote! { #inner_name(__active_input.clone()) });
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            #inner_fn
+
+            #recursion_guard
+
+            let __recursive_index = ::nom_recursive::RECURSIVE_STORAGE
+                .with(|storage| storage.borrow_mut().get(#fn_name_str));
+            let __start_offset = ::nom_recursive::recursive_offset(&#input_ident);
+
+            #memo_lookup
+
+            let __already_growing = ::nom_recursive::GROWING_STORAGE.with(|storage| {
+                storage
+                    .borrow()
+                    .in_head(__recursive_index, __start_offset, __recursive_index)
+            });
+
+            if __already_growing {
+                let __seed: Option<(usize, #output_ty)> = ::nom_recursive::GROWING_STORAGE
+                    .with(|storage| storage.borrow().seed(__recursive_index, __start_offset));
+                return match __seed {
+                    Some((__consumed, __value)) => Ok((
+                        ::nom::Slice::slice(&#input_ident, __consumed..),
+                        __value,
+                    )),
+                    None => {
+                        // No seed has grown yet: this is the base-case re-entry that seeds
+                        // the very first iteration of the growth loop, not an infinite
+                        // cycle, but report the trace built up so far in case growth never
+                        // manages to plant a seed (e.g. a rule that's left-recursive with
+                        // no non-left-recursive alternative to terminate on).
+                        let mut __trace_info =
+                            ::nom_recursive::HasRecursiveInfo::get_recursive_info(&#input_ident);
+                        __trace_info.push_active(__recursive_index);
+                        ::nom_recursive::record_recursion_cycle(
+                            ::nom_recursive::HasRecursiveInfo::recursion_trace(&__trace_info),
+                        );
+                        Err(::nom::Err::Error(::nom::error::Error::new(
+                            #input_ident,
+                            ::nom::error::ErrorKind::Fail,
+                        )))
+                    }
+                };
+            }
+
+            ::nom_recursive::GROWING_STORAGE.with(|storage| {
+                storage
+                    .borrow_mut()
+                    .join_head(__recursive_index, __start_offset, __recursive_index)
+            });
+
+            // Push this rule onto the active chain carried by the input fed to the inner
+            // growth-loop calls (but not the original `#input_ident`, whose info is what
+            // gets returned to the caller -- see the final `Slice::slice` calls below,
+            // which always slice the original, unflagged input). This lets a re-entrant
+            // call further down the stack (the `__already_growing` branch above) report a
+            // trace that includes this rule, the same way `expand_fail_fast` does.
+            let mut __active_info =
+                ::nom_recursive::HasRecursiveInfo::get_recursive_info(&#input_ident);
+            __active_info.push_active(__recursive_index);
+            let __active_input = ::nom_recursive::HasRecursiveInfo::set_recursive_info(
+                #input_ident.clone(),
+                __active_info,
+            );
+
+            let mut __best: Option<(usize, #output_ty)> = None;
+            loop {
+                match #inner_call {
+                    Ok((__rest, __value)) => {
+                        let __consumed =
+                            ::nom_recursive::recursive_offset(&__rest) - __start_offset;
+                        let __grew = __best.as_ref().map_or(true, |(__prev, _)| __consumed > *__prev);
+                        if __grew {
+                            __best = Some((__consumed, __value));
+                            let __seed = __best.clone().unwrap();
+                            ::nom_recursive::GROWING_STORAGE.with(|storage| {
+                                storage.borrow_mut().set_seed(
+                                    __recursive_index,
+                                    __start_offset,
+                                    __seed,
+                                )
+                            });
+                            continue;
+                        }
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            ::nom_recursive::GROWING_STORAGE.with(|storage| {
+                let mut storage = storage.borrow_mut();
+                storage.clear_head(__recursive_index, __start_offset);
+                storage.clear_seed(__recursive_index, __start_offset);
+            });
+
+            #memo_store
+
+            match __best {
+                Some((__consumed, __value)) => {
+                    Ok((::nom::Slice::slice(&#input_ident, __consumed..), __value))
+                }
+                None => Err(::nom::Err::Error(::nom::error::Error::new(
+                    #input_ident,
+                    ::nom::error::ErrorKind::Fail,
+                ))),
+            }
+        }
+    })
+}
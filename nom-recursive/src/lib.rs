@@ -40,26 +40,35 @@
 //!     println!("{:?}", ret.unwrap().1);
 //! }
 //! ```
+//!
+//! The default behavior above only guards against the re-entrant branch overflowing the
+//! stack: `expr_binary` still needs `term` to eventually terminate the recursion, it
+//! can't build a left-associative result on its own. Annotating the rule with
+//! `#[recursive_parser(grow)]` instead runs Warth's seed-growing algorithm (see
+//! [`GrowingStorage`]), so genuinely left-recursive grammars parse and yield a
+//! left-associative tree.
 
 pub use nom_recursive_macros::recursive_parser;
-use std::collections::HashMap;
-
-#[cfg(all(not(feature = "tracer128"), not(feature = "tracer256"),))]
-const RECURSIVE_FLAG_WORDS: usize = 1;
-#[cfg(all(feature = "tracer128", not(feature = "tracer256"),))]
-const RECURSIVE_FLAG_WORDS: usize = 2;
-#[cfg(feature = "tracer256")]
-const RECURSIVE_FLAG_WORDS: usize = 4;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
 
 pub struct RecursiveIndexes {
     indexes: HashMap<&'static str, usize>,
+    names: Vec<&'static str>,
     next: usize,
 }
 
+impl Default for RecursiveIndexes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RecursiveIndexes {
     pub fn new() -> Self {
         RecursiveIndexes {
             indexes: HashMap::new(),
+            names: Vec::new(),
             next: 0,
         }
     }
@@ -69,12 +78,18 @@ impl RecursiveIndexes {
             *x
         } else {
             let new_index = self.next;
-            assert!(new_index < RECURSIVE_FLAG_WORDS * 64, "Recursive tracers exceed the maximum number({}). Consider use feature `tracer128` or `tracer256` to extend it.", RECURSIVE_FLAG_WORDS * 64);
             self.next += 1;
             self.indexes.insert(key, new_index);
+            self.names.push(key);
             new_index
         }
     }
+
+    /// Resolves a rule index back to the rule name it was obtained from in [`get`](Self::get),
+    /// for building a [`RecursionTrace`] when a cycle is detected.
+    pub fn name(&self, index: usize) -> Option<&'static str> {
+        self.names.get(index).copied()
+    }
 }
 
 thread_local!(
@@ -83,11 +98,275 @@ thread_local!(
     }
 );
 
+const DEFAULT_RECURSION_LIMIT: usize = 128;
+
+thread_local!(
+    static RECURSION_DEPTH: core::cell::RefCell<usize> = const { core::cell::RefCell::new(0) };
+);
+
+thread_local!(
+    static RECURSION_LIMIT: core::cell::RefCell<usize> =
+        const { core::cell::RefCell::new(DEFAULT_RECURSION_LIMIT) };
+);
+
+/// Sets the maximum recursion depth for `#[recursive_parser]` functions on the current
+/// thread, analogous to a compiler's `recursion_limit`. This is separate from the
+/// per-position re-entrancy markers in [`RecursiveInfo`]: it also guards pathological but
+/// non-left-recursive input (e.g. thousands of nested parens) against exhausting
+/// resources: an annotated function entered past the limit returns
+/// `nom::Err::Failure` with `ErrorKind::TooLarge` (the [`RecursionLimitError`] from
+/// [`enter_recursion`] is not passed on) instead of aborting the process.
+pub fn set_recursion_limit(limit: usize) {
+    RECURSION_LIMIT.with(|l| *l.borrow_mut() = limit);
+}
+
+/// Increments the recursion-depth counter on entry to a `#[recursive_parser]` function.
+/// Returns `Err(RecursionLimitError)` without incrementing further if doing so would
+/// exceed the configured limit; otherwise returns a guard that decrements the counter
+/// again when dropped, i.e. on exit from the function (including early returns via `?`).
+pub fn enter_recursion() -> Result<RecursionDepthGuard, RecursionLimitError> {
+    RECURSION_DEPTH.with(|depth| {
+        let mut depth = depth.borrow_mut();
+        let limit = RECURSION_LIMIT.with(|l| *l.borrow());
+        if *depth >= limit {
+            return Err(RecursionLimitError { limit });
+        }
+        *depth += 1;
+        Ok(RecursionDepthGuard)
+    })
+}
+
+/// RAII guard returned by [`enter_recursion`] that decrements the recursion-depth
+/// counter on drop.
+pub struct RecursionDepthGuard;
+
+impl Drop for RecursionDepthGuard {
+    fn drop(&mut self) {
+        RECURSION_DEPTH.with(|depth| *depth.borrow_mut() -= 1);
+    }
+}
+
+/// Error returned by [`enter_recursion`] when recursion exceeds the configured
+/// [`set_recursion_limit`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RecursionLimitError {
+    pub limit: usize,
+}
+
+impl std::fmt::Display for RecursionLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "recursion limit ({}) exceeded", self.limit)
+    }
+}
+
+impl std::error::Error for RecursionLimitError {}
+
+thread_local!(
+    pub static GROWING_STORAGE: core::cell::RefCell<crate::GrowingStorage> = {
+        core::cell::RefCell::new(crate::GrowingStorage::new())
+    }
+);
+
+#[cfg(feature = "memoize")]
+thread_local!(
+    pub static MEMO_STORAGE: core::cell::RefCell<crate::MemoStorage> = {
+        core::cell::RefCell::new(crate::MemoStorage::new())
+    }
+);
+
+#[cfg(feature = "spill-stack")]
+thread_local!(
+    pub static STACK_SPILL_CONFIG: core::cell::RefCell<crate::StackSpillConfig> = {
+        core::cell::RefCell::new(crate::StackSpillConfig::default())
+    }
+);
+
+/// Runtime configuration for the `spill-stack` feature, which wires [`stacker`] into the
+/// `#[recursive_parser]` prologue so deeply nested (non-left) recursion never overflows
+/// the native stack: before descending, the prologue checks remaining stack and, if
+/// below `red_zone`, allocates a fresh heap segment of `segment_size` bytes and
+/// continues the parse there.
+#[cfg(feature = "spill-stack")]
+#[derive(Clone, Copy, Debug)]
+pub struct StackSpillConfig {
+    pub red_zone: usize,
+    pub segment_size: usize,
+}
+
+#[cfg(feature = "spill-stack")]
+impl Default for StackSpillConfig {
+    fn default() -> Self {
+        StackSpillConfig {
+            red_zone: 32 * 1024,
+            segment_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Sets the stack red zone and heap segment size used by `#[recursive_parser]` on the
+/// current thread. See [`StackSpillConfig`].
+#[cfg(feature = "spill-stack")]
+pub fn set_stack_spill_config(config: StackSpillConfig) {
+    STACK_SPILL_CONFIG.with(|c| *c.borrow_mut() = config);
+}
+
+/// Runs `f`, first growing the stack onto a fresh heap segment if the remaining stack is
+/// below the configured red zone. Called from the `#[recursive_parser]` prologue before
+/// descending into the parser body.
+#[cfg(feature = "spill-stack")]
+pub fn maybe_grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    let config = STACK_SPILL_CONFIG.with(|c| *c.borrow());
+    stacker::maybe_grow(config.red_zone, config.segment_size, f)
+}
+
+/// Packrat memoization cache for `#[recursive_parser]` functions, gated behind the
+/// `memoize` feature.
+///
+/// Each entry is keyed by `(parser index from [`RecursiveIndexes`], input offset)` and
+/// stores `(bytes consumed, output)`, mirroring [`GrowingStorage`]: the remaining input
+/// is reconstructed by re-slicing the entry span rather than stored directly, so the
+/// cached value stays `'static` even when the span borrows from the original input. On
+/// entry to a recursive-parser function, the expanded prologue checks the cache; on a
+/// hit it returns the stored result (the consumed value and remaining input,
+/// reconstructed from the byte count) without re-running the body, turning repeated
+/// re-parsing of the same sub-expression at the same position from exponential into
+/// linear time. The cached output type must be `Clone + 'static`.
+///
+/// Scoped to a single top-level parse: constructing a fresh [`RecursiveInfo::new`]
+/// clears it, so a stale hit from a previous, unrelated parse on the same thread can't
+/// be returned.
+#[cfg(feature = "memoize")]
+#[derive(Default)]
+pub struct MemoStorage {
+    cache: HashMap<(usize, usize), Box<dyn Any>>,
+}
+
+#[cfg(feature = "memoize")]
+impl MemoStorage {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the memoized `(bytes consumed, output)` for `(index, offset)`, if any.
+    pub fn get<O: Clone + 'static>(&self, index: usize, offset: usize) -> Option<(usize, O)> {
+        self.cache
+            .get(&(index, offset))
+            .and_then(|entry| entry.downcast_ref::<(usize, O)>())
+            .cloned()
+    }
+
+    /// Stores the result for `(index, offset)`.
+    pub fn insert<O: Clone + 'static>(
+        &mut self,
+        index: usize,
+        offset: usize,
+        value: (usize, O),
+    ) {
+        self.cache.insert((index, offset), Box::new(value));
+    }
+
+    /// Drops all cached entries, scoping the cache to a fresh top-level parse. Called
+    /// automatically from [`RecursiveInfo::new`].
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+/// State for Warth's seed-growing algorithm, used by `#[recursive_parser(grow)]` to turn
+/// genuine left recursion (e.g. `expr = expr '+' term`) into a left-associative parse
+/// instead of merely aborting the re-entrant branch.
+///
+/// Seeds are keyed by `(rule index, input offset)` and store `(bytes consumed, output)`
+/// rather than the remaining input itself, so the stored value stays `'static` even when
+/// the span borrows from the original input; the remaining input is reconstructed by
+/// re-slicing the entry span by the stored byte count. When a left-recursive rule is
+/// first entered at a position, a failing seed is planted; re-entry at the same position
+/// returns the current seed instead of recursing again; once the rule body succeeds, the
+/// seed is grown and the body re-run from the same position until a result stops
+/// consuming more input. `heads` is keyed by `(rule index, input offset)` too, so two
+/// unrelated growth cycles for the same rule at different offsets in one parse don't
+/// corrupt each other's head membership. The `#[recursive_parser(grow)]` prologue only
+/// ever joins a rule to its own head; other rules are not added, so each annotated rule
+/// in a mutually (indirectly) recursive cycle runs its own growth loop rather than
+/// growing together with the others.
+///
+/// Both maps are scoped to a single top-level parse: constructing a fresh
+/// [`RecursiveInfo::new`] clears them, so stale seeds from a prior parse on the same
+/// thread can't leak into the next one.
+#[derive(Default)]
+pub struct GrowingStorage {
+    seeds: HashMap<(usize, usize), Box<dyn Any>>,
+    heads: HashMap<(usize, usize), HashSet<usize>>,
+}
+
+impl GrowingStorage {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the `(bytes consumed, output)` seed currently planted for `(index,
+    /// offset)`, if any.
+    pub fn seed<O: Clone + 'static>(&self, index: usize, offset: usize) -> Option<(usize, O)> {
+        self.seeds
+            .get(&(index, offset))
+            .and_then(|seed| seed.downcast_ref::<(usize, O)>())
+            .cloned()
+    }
+
+    /// Plants or grows the seed for `(index, offset)`.
+    pub fn set_seed<O: Clone + 'static>(
+        &mut self,
+        index: usize,
+        offset: usize,
+        value: (usize, O),
+    ) {
+        self.seeds.insert((index, offset), Box::new(value));
+    }
+
+    /// Removes the seed for `(index, offset)` once growing is done.
+    pub fn clear_seed(&mut self, index: usize, offset: usize) {
+        self.seeds.remove(&(index, offset));
+    }
+
+    /// Marks `involved` as part of the left-recursion head rooted at `(head, offset)`.
+    pub fn join_head(&mut self, head: usize, offset: usize, involved: usize) {
+        self.heads.entry((head, offset)).or_default().insert(involved);
+    }
+
+    /// Returns whether `index` has joined the left-recursion head rooted at `(head,
+    /// offset)`.
+    pub fn in_head(&self, head: usize, offset: usize, index: usize) -> bool {
+        self.heads
+            .get(&(head, offset))
+            .is_some_and(|involved| involved.contains(&index))
+    }
+
+    /// Clears the head bookkeeping once the top-level grow for `(head, offset)` has
+    /// finished.
+    pub fn clear_head(&mut self, head: usize, offset: usize) {
+        self.heads.remove(&(head, offset));
+    }
+
+    /// Drops all seeds and head bookkeeping, scoping the cache to a fresh top-level
+    /// parse. Called automatically from [`RecursiveInfo::new`].
+    pub fn clear_all(&mut self) {
+        self.seeds.clear();
+        self.heads.clear();
+    }
+}
+
 /// The type of payload used by recursive tracer
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct RecursiveInfo<T: Clone + Default> {
-    flag: [u64; RECURSIVE_FLAG_WORDS],
+    /// Rule indexes currently on the call stack represented by this `RecursiveInfo`,
+    /// mapped to the input offset they were entered at. Keyed the same way as
+    /// [`GrowingStorage`]/[`MemoStorage`] (by position, not just rule identity) so that
+    /// re-entering a rule further down the same input (ordinary right recursion) is
+    /// allowed, while only re-entering it at the *same* position (true left recursion,
+    /// no input consumed in between) is rejected.
+    active_at: HashMap<usize, usize>,
     copy: T,
+    active: Vec<usize>,
 }
 
 impl<T: Clone + Default> Default for RecursiveInfo<T> {
@@ -97,33 +376,46 @@ impl<T: Clone + Default> Default for RecursiveInfo<T> {
 }
 
 impl<T: Clone + Default> RecursiveInfo<T> {
+    /// Creates a fresh `RecursiveInfo` for the start of a new top-level parse.
+    ///
+    /// This also resets the thread-local per-parse caches ([`GROWING_STORAGE`], and
+    /// [`MEMO_STORAGE`] when the `memoize` feature is enabled) so seeds or cached results
+    /// from a previous parse on the same thread can't leak into this one; the
+    /// rule-name-to-index registry in [`RECURSIVE_STORAGE`] is left untouched since it's
+    /// a process-lifetime registry, not per-parse state.
     pub fn new() -> Self {
+        GROWING_STORAGE.with(|storage| storage.borrow_mut().clear_all());
+        #[cfg(feature = "memoize")]
+        MEMO_STORAGE.with(|storage| storage.borrow_mut().clear());
         RecursiveInfo {
-            flag: [0; RECURSIVE_FLAG_WORDS],
+            active_at: HashMap::new(),
             copy: Default::default(),
+            active: Vec::new(),
         }
     }
 
-    pub fn check_flag(&self, id: usize) -> bool {
-        let upper = id / 64;
-        let lower = id % 64;
-        ((self.flag[upper] >> lower) & 1) == 1
+    /// Returns whether rule `id` is already active at `offset`, i.e. whether entering it
+    /// again here would be true (infinite) left recursion rather than ordinary recursion
+    /// further down the input.
+    pub fn check_flag(&self, id: usize, offset: usize) -> bool {
+        self.active_at.get(&id) == Some(&offset)
     }
 
-    pub fn set_flag(&mut self, id: usize) {
-        let upper = id / 64;
-        let lower = id % 64;
-
-        let val = 1u64 << lower;
-        let mask = !(1u64 << lower);
-
-        self.flag[upper] = (self.flag[upper] & mask) | val;
+    /// Marks rule `id` as active at `offset`.
+    pub fn set_flag(&mut self, id: usize, offset: usize) {
+        self.active_at.insert(id, offset);
     }
 
     pub fn clear_flags(&mut self) {
-        for i in 0..self.flag.len() {
-            self.flag[i] = 0u64;
-        }
+        self.active_at.clear();
+    }
+
+    /// Clears a single rule's active marker, leaving others (e.g. from an enclosing rule
+    /// still on the call stack) untouched. Used on successful exit from a
+    /// `#[recursive_parser]` function, where [`clear_flags`](Self::clear_flags) would
+    /// incorrectly wipe out markers set by callers further up the stack.
+    pub fn clear_flag(&mut self, id: usize) {
+        self.active_at.remove(&id);
     }
 
     pub fn get_copy(&self) -> T {
@@ -133,6 +425,72 @@ impl<T: Clone + Default> RecursiveInfo<T> {
     pub fn set_copy(&mut self, copy: T) {
         self.copy = copy;
     }
+
+    /// Pushes a rule index onto the chain of rules active at the current position, so
+    /// that a cycle detected further down can be reported as a [`RecursionTrace`].
+    pub fn push_active(&mut self, id: usize) {
+        self.active.push(id);
+    }
+
+    /// Pops the most recently pushed rule index, called on exit from a
+    /// `#[recursive_parser]` function.
+    pub fn pop_active(&mut self) {
+        self.active.pop();
+    }
+
+    /// The chain of rule indexes active when this `RecursiveInfo` was observed, in the
+    /// order they were entered.
+    pub fn active_ids(&self) -> &[usize] {
+        &self.active
+    }
+}
+
+/// The chain of rule names that were active when a left-recursion cycle was detected,
+/// e.g. `expr_binary -> expr_binary`. Attach this to a [`nom::error::Error`] (or any
+/// context-carrying nom error type) so a language frontend can report *why* a parse
+/// failed instead of surfacing an opaque nom failure.
+///
+/// Only `#[recursive_parser]`-annotated functions are recorded: an un-annotated dispatch
+/// function the cycle passes through (e.g. a plain top-level `expr` that just calls
+/// `alt((expr_binary, term))`) never appears in the chain, since it never pushes itself
+/// onto the active stack. A cycle that goes `expr -> expr_binary -> expr` therefore
+/// reports as `expr_binary -> expr_binary`, with the un-annotated hop elided.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecursionTrace {
+    rules: Vec<&'static str>,
+}
+
+impl RecursionTrace {
+    pub fn new(rules: Vec<&'static str>) -> Self {
+        RecursionTrace { rules }
+    }
+
+    pub fn rules(&self) -> &[&'static str] {
+        &self.rules
+    }
+}
+
+impl std::fmt::Display for RecursionTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "left-recursion cycle: {}", self.rules.join(" -> "))
+    }
+}
+
+thread_local!(
+    static LAST_CYCLE: core::cell::RefCell<Option<RecursionTrace>> =
+        const { core::cell::RefCell::new(None) };
+);
+
+/// Records the most recently detected left-recursion cycle, so it can be retrieved via
+/// [`take_last_recursion_trace`] once the failed parse has unwound. Called from the
+/// `#[recursive_parser]` prologue when a re-entrant call is about to be rejected.
+pub fn record_recursion_cycle(trace: RecursionTrace) {
+    LAST_CYCLE.with(|cell| *cell.borrow_mut() = Some(trace));
+}
+
+/// Takes (and clears) the most recently recorded left-recursion cycle, if any.
+pub fn take_last_recursion_trace() -> Option<RecursionTrace> {
+    LAST_CYCLE.with(|cell| cell.borrow_mut().take())
 }
 
 /// Trait for recursive tracer
@@ -141,6 +499,18 @@ impl<T: Clone + Default> RecursiveInfo<T> {
 pub trait HasRecursiveInfo<T: Clone + Default> {
     fn get_recursive_info(&self) -> RecursiveInfo<T>;
     fn set_recursive_info(self, info: RecursiveInfo<T>) -> Self;
+
+    /// Resolves the active rule-index stack recorded in the current `RecursiveInfo`
+    /// (via [`RecursiveInfo::push_active`]) into rule names, for reporting a
+    /// [`RecursionTrace`] when a cycle is detected.
+    fn recursion_trace(&self) -> RecursionTrace {
+        let ids = self.get_recursive_info().active_ids().to_vec();
+        let rules = RECURSIVE_STORAGE.with(|storage| {
+            let storage = storage.borrow();
+            ids.iter().filter_map(|id| storage.name(*id)).collect()
+        });
+        RecursionTrace::new(rules)
+    }
 }
 
 impl<T: Clone + Default> HasRecursiveInfo<T> for RecursiveInfo<T> {
@@ -179,3 +549,9 @@ where
         self
     }
 }
+
+/// Returns the byte offset of a span's start within the original top-level input, used
+/// to key [`GrowingStorage`] and [`MemoStorage`] entries by `(rule index, position)`.
+pub fn recursive_offset<T: nom::AsBytes, U>(span: &nom_locate::LocatedSpan<T, U>) -> usize {
+    span.location_offset()
+}
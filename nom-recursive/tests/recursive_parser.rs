@@ -0,0 +1,189 @@
+// `RecursiveInfo`'s per-rule bookkeeping makes `nom::error::Error<Span>` large enough to
+// trip clippy's size heuristic for every parser in this file, annotated or not.
+#![allow(clippy::result_large_err)]
+
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, digit1};
+use nom::combinator::{map, value};
+use nom::sequence::{delimited, tuple};
+use nom::IResult;
+use nom_locate::LocatedSpan;
+use nom_recursive::{
+    recursive_parser, set_recursion_limit, take_last_recursion_trace, RecursiveInfo,
+};
+
+type Span<'a> = LocatedSpan<&'a str, RecursiveInfo<&'a str>>;
+
+// Ordinary right recursion, not left recursion: `parens` only ever recurses into itself
+// after consuming a `(`, so the default (fail-fast) mode should let it nest arbitrarily
+// deep rather than rejecting the second level of nesting.
+#[recursive_parser]
+fn parens(s: Span) -> IResult<Span, ()> {
+    alt((delimited(char('('), parens, char(')')), value((), tag("x"))))(s)
+}
+
+#[test]
+fn fail_fast_mode_allows_deep_non_left_recursion() {
+    for input in ["x", "(x)", "((x))", "((((((((((x))))))))))"] {
+        let span = Span::new_extra(input, RecursiveInfo::new());
+        let (rest, _) = parens(span).unwrap_or_else(|e| panic!("failed to parse {input:?}: {e:?}"));
+        assert_eq!(rest.fragment().len(), 0);
+    }
+}
+
+// Genuine left recursion: `expr_binary` calls `expr` before consuming anything, so the
+// default mode can only ever reject the re-entrant call (see the crate-level example in
+// `src/lib.rs`), never build a left-associative parse on its own.
+// `#[recursive_parser(grow)]` instead runs Warth's seed-growing algorithm to the
+// expected result.
+fn expr(s: Span) -> IResult<Span, String> {
+    alt((expr_binary, term))(s)
+}
+
+#[recursive_parser(grow)]
+fn expr_binary(s: Span) -> IResult<Span, String> {
+    let (s, x) = expr(s)?;
+    let (s, _) = char('+')(s)?;
+    let (s, y) = term(s)?;
+    Ok((s, format!("({x}+{y})")))
+}
+
+fn term(s: Span) -> IResult<Span, String> {
+    map(digit1, |d: Span| d.fragment().to_string())(s)
+}
+
+#[test]
+fn grow_mode_parses_left_recursive_expression() {
+    let span = Span::new_extra("1+2+3", RecursiveInfo::new());
+    let (rest, value) = expr(span).expect("should parse");
+    assert_eq!(rest.fragment().len(), 0);
+    assert_eq!(value, "((1+2)+3)");
+}
+
+// A left-recursive rule with no alternative that can terminate growth: every attempt
+// just recurses into itself again, so even seed-growing can't plant a successful seed.
+#[recursive_parser(grow)]
+fn ungrowable(s: Span) -> IResult<Span, char> {
+    map(ungrowable, |c| c)(s)
+}
+
+#[test]
+fn grow_mode_reports_a_cycle_trace_when_it_cannot_grow() {
+    let span = Span::new_extra("x", RecursiveInfo::new());
+    assert!(ungrowable(span).is_err());
+    let trace = take_last_recursion_trace().expect("expected a recorded recursion trace");
+    assert_eq!(trace.rules(), &["ungrowable", "ungrowable"]);
+}
+
+#[test]
+fn grow_mode_cycle_trace_elides_unannotated_dispatch() {
+    // `expr` itself isn't annotated, so the chain only shows the annotated hop that
+    // repeats -- see the limitation documented on `RecursionTrace`.
+    let span = Span::new_extra("", RecursiveInfo::new());
+    assert!(expr_binary(span).is_err());
+    let trace = take_last_recursion_trace().expect("expected a recorded recursion trace");
+    assert_eq!(trace.rules(), &["expr_binary", "expr_binary"]);
+}
+
+#[recursive_parser]
+fn unbounded_right_recursion(s: Span) -> IResult<Span, ()> {
+    alt((
+        map(tuple((char('a'), unbounded_right_recursion)), |_| ()),
+        value((), char('b')),
+    ))(s)
+}
+
+#[test]
+fn recursion_limit_rejects_pathologically_deep_non_left_recursion() {
+    set_recursion_limit(16);
+    let input = "a".repeat(100) + "b";
+    let span = Span::new_extra(&input, RecursiveInfo::new());
+    assert!(
+        unbounded_right_recursion(span).is_err(),
+        "expected the depth limiter to reject a 100-deep chain at a limit of 16"
+    );
+    set_recursion_limit(128);
+}
+
+#[cfg(feature = "memoize")]
+mod memoize {
+    use super::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[recursive_parser(grow)]
+    fn counted_expr(s: Span) -> IResult<Span, String> {
+        CALLS.with(|c| c.set(c.get() + 1));
+        alt((
+            map(
+                tuple((counted_expr, char('+'), counted_term)),
+                |(x, _, y)| format!("({x}+{y})"),
+            ),
+            counted_term,
+        ))(s)
+    }
+
+    fn counted_term(s: Span) -> IResult<Span, String> {
+        map(digit1, |d: Span| d.fragment().to_string())(s)
+    }
+
+    // Re-enters `counted_expr` at offset 0 twice: once via each `alt` branch below, since
+    // the first requires a trailing "z" that isn't present.
+    fn counted_top(s: Span) -> IResult<Span, String> {
+        alt((
+            map(tuple((counted_expr, char('z'))), |(e, _)| e),
+            map(tuple((counted_expr, char('y'))), |(e, _)| e),
+        ))(s)
+    }
+
+    #[test]
+    fn memoize_caches_repeated_grow_mode_entry_at_the_same_offset() {
+        let span = Span::new_extra("1+2+3y", RecursiveInfo::new());
+        let (rest, value) = counted_top(span).expect("should parse");
+        assert_eq!(rest.fragment().len(), 0);
+        assert_eq!(value, "((1+2)+3)");
+        // The first entry runs the body four times (seeds "1", "(1+2)", "((1+2)+3)", then
+        // one attempt that stops growing); the second entry is a cache hit and adds none.
+        assert_eq!(CALLS.with(|c| c.get()), 4);
+
+        // Re-parsing a fresh, unrelated input starts from a fresh `RecursiveInfo`, which
+        // clears the cache -- so this second parse must fully recompute rather than
+        // return a stale hit left over from the first.
+        CALLS.with(|c| c.set(0));
+        let span2 = Span::new_extra("9+8", RecursiveInfo::new());
+        let (_, value2) = counted_expr(span2).expect("should parse");
+        assert_eq!(value2, "(9+8)");
+        assert!(CALLS.with(|c| c.get()) > 0);
+    }
+}
+
+#[cfg(feature = "spill-stack")]
+mod spill_stack {
+    use super::*;
+
+    #[recursive_parser]
+    fn deep_parens(s: Span) -> IResult<Span, ()> {
+        alt((
+            delimited(char('('), deep_parens, char(')')),
+            value((), tag("x")),
+        ))(s)
+    }
+
+    #[test]
+    fn spill_stack_survives_deep_non_left_recursion() {
+        // `spill-stack` only guards against overflowing the *native* stack; the separate
+        // recursion-depth limiter (see `set_recursion_limit`) would otherwise reject a
+        // chain this deep well before the stack itself would be at risk.
+        set_recursion_limit(100_000);
+        let depth = 20_000;
+        let input = "(".repeat(depth) + "x" + &")".repeat(depth);
+        let span = Span::new_extra(&input, RecursiveInfo::new());
+        let (rest, _) = deep_parens(span).expect("should parse without overflowing the stack");
+        assert_eq!(rest.fragment().len(), 0);
+        set_recursion_limit(128);
+    }
+}